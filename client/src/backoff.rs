@@ -0,0 +1,116 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Exponential backoff with jitter for reconnect loops: starts at `base`,
+/// doubles on each failure, caps at `max`, and applies up to 50% jitter so
+/// many consumers restarting at once don't all hammer the server in lockstep.
+/// The jitter is seeded per-instance (not just from the shared attempt
+/// counter), so two `Backoff`s that fail at the same attempt -- e.g. every
+/// subscriber disconnected at once by an RPC restart -- don't compute the
+/// identical delay and reconnect in lockstep anyway.
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+    instance_seed: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Backoff {
+            base,
+            max,
+            attempt: 0,
+            instance_seed: next_instance_seed(),
+        }
+    }
+
+    /// Returns the delay to wait before the next attempt, and advances the
+    /// internal attempt counter.
+    pub fn next_delay(&mut self) -> Duration {
+        let exp = self.base.saturating_mul(1u32 << self.attempt.min(16));
+        let capped = exp.min(self.max);
+        let jitter = jitter_fraction(self.instance_seed.wrapping_add(self.attempt));
+        self.attempt += 1;
+        capped.mul_f64(0.5 + 0.5 * jitter)
+    }
+
+    /// Call after a successful connection to reset the backoff for the
+    /// next time it fails.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// A seed unique to this `Backoff` instance, avoiding a dependency on `rand`
+/// for a single reconnect loop: combines the current time (real entropy)
+/// with a process-wide counter (so two instances created in the same
+/// instant, e.g. at startup, still get distinct seeds).
+fn next_instance_seed() -> u32 {
+    static NEXT_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let counter = NEXT_COUNTER.fetch_add(1, Ordering::Relaxed);
+    nanos ^ counter.wrapping_mul(2_654_435_761)
+}
+
+/// Cheap deterministic jitter source given a seed.
+fn jitter_fraction(seed: u32) -> f64 {
+    let x = seed.wrapping_mul(2_654_435_761);
+    (x % 1000) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_delay_starts_at_or_above_half_base() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(30));
+        let delay = backoff.next_delay();
+        assert!(delay >= Duration::from_millis(50));
+        assert!(delay <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn next_delay_never_exceeds_max() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+        for _ in 0..64 {
+            let delay = backoff.next_delay();
+            assert!(delay <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn two_instances_at_the_same_attempt_do_not_compute_the_identical_delay() {
+        // The scenario the `Backoff` doc calls out: an RPC restart disconnects
+        // every subscriber at once, so every `Backoff` starts at attempt 0
+        // simultaneously. Before the per-instance seed, `next_delay()` was a
+        // pure function of `attempt` alone and every instance reconnected in
+        // lockstep.
+        let delays: Vec<Duration> = (0..8)
+            .map(|_| {
+                let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(30));
+                backoff.next_delay()
+            })
+            .collect();
+        assert!(delays.iter().any(|d| *d != delays[0]));
+    }
+
+    #[test]
+    fn reset_returns_to_the_initial_delay_range() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(30));
+        for _ in 0..10 {
+            backoff.next_delay();
+        }
+        backoff.reset();
+        let delay = backoff.next_delay();
+        assert!(delay >= Duration::from_millis(50));
+        assert!(delay <= Duration::from_millis(100));
+    }
+}
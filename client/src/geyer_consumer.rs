@@ -1,27 +1,91 @@
 use std::{
+    collections::HashSet,
+    net::SocketAddr,
     str::FromStr,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use crossbeam::channel::Sender;
 use geyser_proto::geyser_client::GeyserClient;
-use tokio::runtime::Runtime;
+use log::warn;
+use solana_sdk::pubkey::Pubkey;
+use tokio::{runtime::Runtime, sync::Notify};
+use tokio_stream::StreamExt;
 use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
 
 use crate::{
-    geyser_proto,
-    types::{AccountUpdate, PartialAccountUpdate, SlotUpdate},
+    backoff::Backoff,
+    geyser_proto::{self, SubscribeRequestFilterAccounts, SubscribeRequestFilterMemcmp},
+    proto_convert::account_update_from_proto,
+    source::GeyserSource,
+    types::{AccountUpdate, ClusterNode, Memcmp, PartialAccountUpdate, SlotUpdate, StreamEvent},
 };
 
+// No `CommitmentLevel` parameter here: the server doesn't gate account-write
+// broadcasts on commitment (see `update_account` in geyser_plugin_grpc.rs),
+// so threading one through would silently promise a safety/latency
+// tradeoff callers don't actually get. Add it back once the server buffers
+// writes per slot and releases them at the requested commitment.
+
+fn account_filter_request(
+    owners: &[Pubkey],
+    datasize: Option<u64>,
+    memcmp: &[Memcmp],
+) -> geyser_proto::SubscribeRequest {
+    let filter = SubscribeRequestFilterAccounts {
+        account: Vec::new(),
+        owner: owners.iter().map(|pubkey| pubkey.to_bytes().to_vec()).collect(),
+        data_size: datasize,
+        memcmp: memcmp
+            .iter()
+            .map(|m| SubscribeRequestFilterMemcmp {
+                offset: m.offset as u64,
+                bytes: m.bytes.clone(),
+            })
+            .collect(),
+    };
+    geyser_proto::SubscribeRequest {
+        account_filters: std::iter::once(("default".to_string(), filter)).collect(),
+        ..Default::default()
+    }
+}
+
+/// Builds a request that matches exactly `accounts`. Callers must not call
+/// this with an empty set: on the wire an empty `account` list means
+/// match-everything (see `server/src/filter.rs`), the opposite of what an
+/// on-demand subscriber wants once it has untracked every account — they
+/// should park the subscription instead (see `subscribe_accounts_on_demand`).
+fn accounts_filter_request(accounts: &HashSet<Vec<u8>>) -> geyser_proto::SubscribeRequest {
+    debug_assert!(!accounts.is_empty());
+    let filter = SubscribeRequestFilterAccounts {
+        account: accounts.iter().cloned().collect(),
+        owner: Vec::new(),
+        data_size: None,
+        memcmp: Vec::new(),
+    };
+    geyser_proto::SubscribeRequest {
+        account_filters: std::iter::once(("on_demand".to_string(), filter)).collect(),
+        ..Default::default()
+    }
+}
+
 pub struct GeyserConsumer<T> {
     inner: Arc<Mutex<GeyserClient<T>>>,
     runtime: Runtime,
+    /// Only set when built via `GeyserConsumer::<Channel>::connect`; lets
+    /// supervised subscriptions rebuild the transport on reconnect.
+    endpoint: Option<Endpoint>,
 }
 
 impl<T> GeyserConsumer<T> {
     pub fn new(client: GeyserClient<T>, runtime: Runtime) -> Self {
         let inner = Arc::new(Mutex::new(client));
-        Self { inner, runtime }
+        Self {
+            inner,
+            runtime,
+            endpoint: None,
+        }
     }
 
     pub fn subscribe_account_updates(&self, tx: Sender<AccountUpdate>) {
@@ -50,7 +114,7 @@ impl<T> GeyserConsumer<T> {
         });
     }
 
-    pub fn subscribe_slot_updates(self, tx: Sender<SlotUpdate>) {
+    pub fn subscribe_slot_updates(&self, tx: Sender<SlotUpdate>) {
         let mut inner = self.inner.lock().unwrap();
         let stream = self.runtime.block_on(async {
             inner
@@ -59,4 +123,372 @@ impl<T> GeyserConsumer<T> {
                 .into_inner()
         });
     }
+
+    /// Like `subscribe_account_updates`, but only streams accounts owned by
+    /// one of `owners` and matching the given `datasize`/`memcmp`
+    /// constraints, with matching done server-side. Lets a consumer
+    /// subscribe to, say, all token accounts for one mint without pulling
+    /// gigabytes of unrelated data.
+    pub fn subscribe_account_updates_filtered(
+        &self,
+        tx: Sender<AccountUpdate>,
+        owners: Vec<Pubkey>,
+        datasize: Option<u64>,
+        memcmp: Vec<Memcmp>,
+    ) {
+        let request = account_filter_request(&owners, datasize, &memcmp);
+
+        let mut inner = self.inner.lock().unwrap();
+        let mut stream = self
+            .runtime
+            .block_on(async { inner.subscribe_account_updates(request).await })
+            .expect("failed to subscribe to filtered account updates")
+            .into_inner();
+
+        self.runtime.spawn(async move {
+            loop {
+                let update = match stream.next().await {
+                    Some(Ok(update)) => update,
+                    Some(Err(err)) => {
+                        warn!("filtered account update stream error: {:?}", err);
+                        break;
+                    }
+                    None => break,
+                };
+
+                if tx.send(account_update_from_proto(update)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Like `subscribe_account_updates_filtered`, but also appends every
+    /// received update to `record_path` (see `recorder::Recorder`) before
+    /// forwarding it to `tx`, for later replay via `recorder::replay_into`.
+    /// Useful for capturing a deterministic slice of a live feed to debug
+    /// indexer logic or build a regression test from.
+    pub fn subscribe_account_updates_recording(
+        &self,
+        tx: Sender<AccountUpdate>,
+        record_path: impl AsRef<std::path::Path>,
+        owners: Vec<Pubkey>,
+        datasize: Option<u64>,
+        memcmp: Vec<Memcmp>,
+    ) -> std::io::Result<()> {
+        let recorder = Arc::new(crate::recorder::Recorder::create(record_path)?);
+        let request = account_filter_request(&owners, datasize, &memcmp);
+
+        let mut inner = self.inner.lock().unwrap();
+        let mut stream = self
+            .runtime
+            .block_on(async { inner.subscribe_account_updates(request).await })
+            .expect("failed to subscribe to filtered account updates")
+            .into_inner();
+
+        self.runtime.spawn(async move {
+            loop {
+                let update = match stream.next().await {
+                    Some(Ok(update)) => update,
+                    Some(Err(err)) => {
+                        warn!("recording account update stream error: {:?}", err);
+                        break;
+                    }
+                    None => break,
+                };
+
+                let account_update = account_update_from_proto(update);
+                if let Err(err) = recorder.record(&account_update) {
+                    warn!("failed to record account update: {:?}", err);
+                }
+
+                if tx.send(account_update).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Streams the validator set (pubkey, gossip/TPU/TVU addresses, shred
+    /// version, software version) so a caller can track the cluster from
+    /// the same connector it already uses for accounts, instead of polling
+    /// `getClusterNodes` over JSON-RPC.
+    pub fn subscribe_cluster_info(&self, tx: Sender<ClusterNode>) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut stream = self
+            .runtime
+            .block_on(async { inner.subscribe_cluster_info(EmptyRequest {}).await })
+            .expect("failed to subscribe to cluster info")
+            .into_inner();
+
+        self.runtime.spawn(async move {
+            loop {
+                let node = match stream.next().await {
+                    Some(Ok(node)) => node,
+                    Some(Err(err)) => {
+                        warn!("cluster info stream error: {:?}", err);
+                        break;
+                    }
+                    None => break,
+                };
+
+                let parse_addr = |addr: &str| SocketAddr::from_str(addr).ok();
+                let cluster_node = ClusterNode {
+                    pubkey: Pubkey::new(&node.pubkey),
+                    gossip: parse_addr(&node.gossip),
+                    tpu: parse_addr(&node.tpu),
+                    tvu: parse_addr(&node.tvu),
+                    shred_version: node.shred_version as u16,
+                    version: node.version,
+                    is_departed: node.is_departed,
+                };
+
+                if tx.send(cluster_node).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Delegates to the base (non-filtered) subscribe methods, so code written
+/// against `GeyserSource` works unchanged whether the consumer underneath is
+/// talking gRPC or QUIC.
+impl GeyserSource for GeyserConsumer<Channel> {
+    fn subscribe_account_updates(&self, tx: Sender<AccountUpdate>) {
+        GeyserConsumer::subscribe_account_updates(self, tx)
+    }
+
+    fn subscribe_partial_account_updates(&self, tx: Sender<PartialAccountUpdate>, skip_vote_accounts: bool) {
+        GeyserConsumer::subscribe_partial_account_updates(self, tx, skip_vote_accounts)
+    }
+
+    fn subscribe_slot_updates(&self, tx: Sender<SlotUpdate>) {
+        GeyserConsumer::subscribe_slot_updates(self, tx)
+    }
+}
+
+impl GeyserConsumer<Channel> {
+    /// Connects once and remembers `endpoint`, so a later
+    /// `subscribe_account_updates_supervised` call can rebuild the channel
+    /// after the connection drops.
+    pub fn connect(endpoint: Endpoint, runtime: Runtime) -> anyhow::Result<Self> {
+        let channel = runtime.block_on(endpoint.connect())?;
+        let inner = Arc::new(Mutex::new(GeyserClient::new(channel)));
+        Ok(Self {
+            inner,
+            runtime,
+            endpoint: Some(endpoint),
+        })
+    }
+
+    /// Like `subscribe_account_updates_filtered`, but reconnects with
+    /// exponential backoff instead of giving up when the stream ends or the
+    /// connection is lost. Every update is wrapped in `StreamEvent`: a
+    /// reconnect whose first update lands past the slot right after the one
+    /// last seen emits `StreamEvent::Gap` first, so the consumer knows to
+    /// reconcile its state rather than assume nothing was missed.
+    pub fn subscribe_account_updates_supervised(
+        &self,
+        tx: Sender<StreamEvent<AccountUpdate>>,
+        owners: Vec<Pubkey>,
+        datasize: Option<u64>,
+        memcmp: Vec<Memcmp>,
+    ) {
+        let endpoint = self
+            .endpoint
+            .clone()
+            .expect("subscribe_account_updates_supervised requires a consumer built via connect()");
+
+        self.runtime.spawn(async move {
+            let mut backoff = Backoff::new(Duration::from_millis(500), Duration::from_secs(30));
+            let mut last_slot: Option<u64> = None;
+
+            loop {
+                let channel = match endpoint.connect().await {
+                    Ok(channel) => channel,
+                    Err(err) => {
+                        warn!("supervised subscribe: connect failed: {:?}", err);
+                        tokio::time::sleep(backoff.next_delay()).await;
+                        continue;
+                    }
+                };
+                let mut client = GeyserClient::new(channel);
+                let request = account_filter_request(&owners, datasize, &memcmp);
+                let mut stream = match client.subscribe_account_updates(request).await {
+                    Ok(response) => response.into_inner(),
+                    Err(err) => {
+                        warn!("supervised subscribe: request failed: {:?}", err);
+                        tokio::time::sleep(backoff.next_delay()).await;
+                        continue;
+                    }
+                };
+                backoff.reset();
+
+                let mut first_update = true;
+                loop {
+                    let update = match stream.next().await {
+                        Some(Ok(update)) => update,
+                        Some(Err(err)) => {
+                            warn!("supervised account update stream error: {:?}", err);
+                            break;
+                        }
+                        None => break,
+                    };
+
+                    if first_update {
+                        first_update = false;
+                        if let Some(expected) = last_slot.map(|slot| slot + 1) {
+                            if update.slot > expected && tx.send(StreamEvent::Gap { resume_slot: update.slot }).is_err() {
+                                return;
+                            }
+                        }
+                    }
+
+                    last_slot = Some(update.slot);
+                    if tx
+                        .send(StreamEvent::Update(account_update_from_proto(update)))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+
+                tokio::time::sleep(backoff.next_delay()).await;
+            }
+        });
+    }
+
+    /// Starts an account-on-demand subscription and returns a handle that
+    /// can grow or shrink the watched set while the subscription is live.
+    /// Adding or removing accounts triggers a resubscribe on the same
+    /// connection; the old stream is dropped only once the new subscribe
+    /// call has succeeded. Updates the old stream had already received but
+    /// not yet yielded at that moment are discarded with it.
+    pub fn subscribe_accounts_on_demand(
+        &self,
+        tx: Sender<AccountUpdate>,
+        initial: Vec<Pubkey>,
+    ) -> AccountSubscriptionHandle {
+        let tracked = Arc::new(Mutex::new(
+            initial.iter().map(|pubkey| pubkey.to_bytes().to_vec()).collect::<HashSet<_>>(),
+        ));
+        let notify = Arc::new(Notify::new());
+        let handle = AccountSubscriptionHandle {
+            tracked: tracked.clone(),
+            notify: notify.clone(),
+        };
+
+        let endpoint = self
+            .endpoint
+            .clone()
+            .expect("subscribe_accounts_on_demand requires a consumer built via connect()");
+
+        self.runtime.spawn(async move {
+            let mut backoff = Backoff::new(Duration::from_millis(500), Duration::from_secs(30));
+
+            loop {
+                // Nothing tracked: park without an open subscription rather
+                // than send a filter that the server reads as match-all.
+                while tracked.lock().unwrap().is_empty() {
+                    notify.notified().await;
+                }
+
+                let channel = match endpoint.connect().await {
+                    Ok(channel) => channel,
+                    Err(err) => {
+                        warn!("on-demand subscribe: connect failed: {:?}", err);
+                        tokio::time::sleep(backoff.next_delay()).await;
+                        continue;
+                    }
+                };
+                let mut client = GeyserClient::new(channel);
+                let snapshot = tracked.lock().unwrap().clone();
+                let request = accounts_filter_request(&snapshot);
+                let mut stream = match client.subscribe_account_updates(request).await {
+                    Ok(response) => response.into_inner(),
+                    Err(err) => {
+                        warn!("on-demand subscribe: request failed: {:?}", err);
+                        tokio::time::sleep(backoff.next_delay()).await;
+                        continue;
+                    }
+                };
+                backoff.reset();
+                let mut parked = false;
+
+                'stream: loop {
+                    tokio::select! {
+                        item = stream.next() => {
+                            match item {
+                                Some(Ok(update)) => {
+                                    if tx.send(account_update_from_proto(update)).is_err() {
+                                        return;
+                                    }
+                                }
+                                Some(Err(err)) => {
+                                    warn!("on-demand account update stream error: {:?}", err);
+                                    break 'stream;
+                                }
+                                None => break 'stream,
+                            }
+                        }
+                        _ = notify.notified() => {
+                            let snapshot = tracked.lock().unwrap().clone();
+                            if snapshot.is_empty() {
+                                // Everything was untracked: drop the stream
+                                // and go back to parking above instead of
+                                // resubscribing with a match-all filter.
+                                parked = true;
+                                break 'stream;
+                            }
+                            let request = accounts_filter_request(&snapshot);
+                            match client.subscribe_account_updates(request).await {
+                                Ok(response) => stream = response.into_inner(),
+                                Err(err) => warn!("on-demand resubscribe failed, keeping old stream: {:?}", err),
+                            }
+                        }
+                    }
+                }
+
+                if !parked {
+                    tokio::time::sleep(backoff.next_delay()).await;
+                }
+            }
+        });
+
+        handle
+    }
+}
+
+/// Grows or shrinks the watched set of a live
+/// `GeyserConsumer::subscribe_accounts_on_demand` subscription.
+pub struct AccountSubscriptionHandle {
+    tracked: Arc<Mutex<HashSet<Vec<u8>>>>,
+    notify: Arc<Notify>,
+}
+
+impl AccountSubscriptionHandle {
+    pub fn add_accounts(&self, keys: Vec<Pubkey>) {
+        let mut tracked = self.tracked.lock().unwrap();
+        let changed = keys
+            .into_iter()
+            .fold(false, |changed, key| tracked.insert(key.to_bytes().to_vec()) || changed);
+        drop(tracked);
+        if changed {
+            self.notify.notify_one();
+        }
+    }
+
+    pub fn remove_accounts(&self, keys: Vec<Pubkey>) {
+        let mut tracked = self.tracked.lock().unwrap();
+        let changed = keys
+            .into_iter()
+            .fold(false, |changed, key| tracked.remove(&key.to_bytes().to_vec()) || changed);
+        drop(tracked);
+        if changed {
+            self.notify.notify_one();
+        }
+    }
 }
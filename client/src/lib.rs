@@ -1,6 +1,11 @@
+pub mod backoff;
 pub mod geyer_consumer;
 pub mod grpc_plugin_source;
 pub mod metrics;
+pub(crate) mod proto_convert;
+pub mod quic_consumer;
+pub mod recorder;
+pub mod source;
 pub mod types;
 
 use serde_derive::Deserialize;
@@ -0,0 +1,36 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    geyser_proto,
+    types::{AccountUpdate, PartialAccountUpdate},
+};
+
+/// Shared by `geyer_consumer` (gRPC) and `quic_consumer` (QUIC), which
+/// otherwise decode the exact same wire messages into the exact same types.
+pub(crate) fn account_update_from_proto(update: geyser_proto::AccountUpdate) -> AccountUpdate {
+    AccountUpdate {
+        pubkey: Pubkey::new(&update.pubkey),
+        owner: Pubkey::new(&update.owner),
+        data: update.data,
+        tx_signature: update.tx_signature,
+        slot: update.slot,
+        lamports: update.lamports,
+        rent_epoch: update.rent_epoch,
+        seq: update.write_version,
+        is_executable: update.executable,
+        is_startup: update.is_startup,
+        is_selected: true,
+    }
+}
+
+pub(crate) fn partial_account_update_from_proto(
+    update: geyser_proto::PartialAccountUpdate,
+) -> PartialAccountUpdate {
+    PartialAccountUpdate {
+        pubkey: Pubkey::new(&update.pubkey),
+        tx_signature: update.tx_signature,
+        slot: update.slot,
+        seq: update.write_version,
+        is_startup: update.is_startup,
+    }
+}
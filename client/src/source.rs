@@ -0,0 +1,14 @@
+use crossbeam::channel::Sender;
+
+use crate::types::{AccountUpdate, PartialAccountUpdate, SlotUpdate};
+
+/// A pluggable stream source yielding the three base subscription kinds into
+/// crossbeam channels. `GeyserConsumer<Channel>` implements this over tonic
+/// gRPC; `QuicGeyserConsumer` implements it over QUIC. Code written against
+/// `GeyserSource` instead of a concrete consumer type can switch transports
+/// without change, picking whichever one the plugin it talks to exposes.
+pub trait GeyserSource: Send + Sync {
+    fn subscribe_account_updates(&self, tx: Sender<AccountUpdate>);
+    fn subscribe_partial_account_updates(&self, tx: Sender<PartialAccountUpdate>, skip_vote_accounts: bool);
+    fn subscribe_slot_updates(&self, tx: Sender<SlotUpdate>);
+}
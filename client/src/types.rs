@@ -1,5 +1,9 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
 use solana_sdk::{pubkey::Pubkey, slot_hashes::Slot};
 
+#[derive(Serialize, Deserialize)]
 pub struct AccountUpdate {
     pub pubkey: Pubkey,
     pub owner: Pubkey,
@@ -8,7 +12,7 @@ pub struct AccountUpdate {
     pub slot: Slot,
     pub lamports: u64,
     pub rent_epoch: u64,
-    pub seq: u8,
+    pub seq: u64,
     pub is_executable: bool,
     pub is_startup: bool,
     pub is_selected: bool,
@@ -18,18 +22,50 @@ pub struct PartialAccountUpdate {
     pub pubkey: Pubkey,
     pub tx_signature: Option<String>,
     pub slot: Slot,
-    pub seq: u8,
+    pub seq: u64,
     pub is_startup: bool,
 }
 
-enum Status {
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Status {
     Confirmed,
     Processed,
     Rooted,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct SlotUpdate {
     pub parent_slot: Option<Slot>,
     pub slot: Slot,
     pub status: Status,
 }
+
+/// Wraps a stream item with an out-of-band gap marker. Supervised
+/// subscriptions emit `Gap` after a reconnect whose first update lands
+/// past the expected slot, so a consumer knows to reload state rather than
+/// assume the feed was continuous.
+pub enum StreamEvent<T> {
+    Update(T),
+    Gap { resume_slot: Slot },
+}
+
+/// A `memcmp { offset, bytes }` constraint for
+/// `GeyserConsumer::subscribe_account_updates_filtered`: the account data
+/// must match `bytes` exactly at `offset`.
+pub struct Memcmp {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// A gossip node, as tracked by `GeyserConsumer::subscribe_cluster_info`.
+/// `is_departed` distinguishes a node leaving the cluster from an upsert,
+/// so a consumer can maintain a live `HashMap<Pubkey, ClusterNode>`.
+pub struct ClusterNode {
+    pub pubkey: Pubkey,
+    pub gossip: Option<SocketAddr>,
+    pub tpu: Option<SocketAddr>,
+    pub tvu: Option<SocketAddr>,
+    pub shred_version: u16,
+    pub version: Option<String>,
+    pub is_departed: bool,
+}
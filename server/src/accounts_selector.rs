@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+
+use log::*;
+use serde_json;
+
+/// The set of accounts and owners the plugin streams out. Built once from
+/// config in `on_load`, and from then on mutable behind an `RwLock` so the
+/// admin service can register/unregister accounts at runtime without a
+/// validator reload.
+#[derive(Debug, Default)]
+pub struct AccountsSelector {
+    /// Streams all accounts set here.
+    pub accounts: HashSet<Vec<u8>>,
+
+    /// Streams out accounts that have an owner set here.
+    pub owners: HashSet<Vec<u8>>,
+
+    /// Accounts registered at runtime through the admin service, as opposed
+    /// to ones configured up front. Tracked separately so on-demand entries
+    /// can be evicted on a TTL while statically configured ones never are.
+    pub on_demand_accounts: HashSet<Vec<u8>>,
+
+    /// Owners registered at runtime through the admin service, as opposed to
+    /// ones configured up front. Tracked separately from `owners` (same
+    /// reason as `on_demand_accounts`) so a hot-reload swap can carry them
+    /// forward instead of silently dropping them on the next config edit.
+    pub on_demand_owners: HashSet<Vec<u8>>,
+
+    /// Streams all accounts out if true.
+    pub select_all_accounts: bool,
+}
+
+impl AccountsSelector {
+    /// Fails with `Err` instead of panicking on a malformed base58 key: this
+    /// runs inside `on_load`, across the plugin's FFI boundary, where an
+    /// unwind is UB and would take the whole validator process down on a
+    /// config typo.
+    pub fn new(accounts: &[String], owners: &[String]) -> Result<Self, String> {
+        info!(
+            "Creating AccountsSelector from accounts: {:?}, owners: {:?}",
+            accounts, owners
+        );
+
+        let select_all_accounts = accounts.iter().any(|key| key == "*");
+        if select_all_accounts {
+            return Ok(AccountsSelector {
+                select_all_accounts,
+                ..AccountsSelector::default()
+            });
+        }
+
+        let decode_all = |keys: &[String]| -> Result<HashSet<Vec<u8>>, String> {
+            keys.iter()
+                .map(|key| {
+                    bs58::decode(key)
+                        .into_vec()
+                        .map_err(|err| format!("invalid base58 pubkey {:?}: {:?}", key, err))
+                })
+                .collect()
+        };
+        let accounts = decode_all(accounts)?;
+        let owners = decode_all(owners)?;
+        Ok(AccountsSelector {
+            accounts,
+            owners,
+            select_all_accounts,
+            ..AccountsSelector::default()
+        })
+    }
+
+    pub fn is_account_selected(&self, account: &[u8], owner: &[u8]) -> bool {
+        self.select_all_accounts
+            || self.accounts.contains(account)
+            || self.owners.contains(owner)
+            || self.on_demand_accounts.contains(account)
+            || self.on_demand_owners.contains(owner)
+    }
+
+    pub fn is_on_demand(&self, account: &[u8]) -> bool {
+        self.on_demand_accounts.contains(account)
+    }
+
+    /// Register a pubkey for on-demand streaming. Returns false if it was
+    /// already tracked (statically or on-demand).
+    pub fn register_account(&mut self, account: Vec<u8>) -> bool {
+        if self.accounts.contains(&account) {
+            return false;
+        }
+        self.on_demand_accounts.insert(account)
+    }
+
+    /// Unregister a pubkey that was previously added on demand. Has no
+    /// effect on accounts configured statically at load time.
+    pub fn unregister_account(&mut self, account: &[u8]) -> bool {
+        self.on_demand_accounts.remove(account)
+    }
+
+    /// Register an owner for runtime streaming. Returns false if it was
+    /// already tracked (statically or on-demand).
+    pub fn register_owner(&mut self, owner: Vec<u8>) -> bool {
+        if self.owners.contains(&owner) {
+            return false;
+        }
+        self.on_demand_owners.insert(owner)
+    }
+
+    /// Unregister an owner that was previously added at runtime. Has no
+    /// effect on owners configured statically at load time.
+    pub fn unregister_owner(&mut self, owner: &[u8]) -> bool {
+        self.on_demand_owners.remove(owner)
+    }
+
+    /// Parses the `accounts_selector` block out of a plugin config file,
+    /// without panicking on a malformed document. Used both for the
+    /// initial load and for hot-reloading the config at runtime, where a
+    /// bad edit must not be able to take the stream down.
+    pub fn from_config_value(config: &serde_json::Value) -> Result<Self, String> {
+        let accounts_selector = &config["accounts_selector"];
+        if accounts_selector.is_null() {
+            return Ok(AccountsSelector::default());
+        }
+
+        let parse_list = |key: &str| -> Result<Vec<String>, String> {
+            let value = &accounts_selector[key];
+            if value.is_null() {
+                return Ok(Vec::default());
+            }
+            value
+                .as_array()
+                .ok_or_else(|| format!("accounts_selector.{} must be an array", key))?
+                .iter()
+                .map(|entry| {
+                    entry
+                        .as_str()
+                        .map(|s| s.to_string())
+                        .ok_or_else(|| format!("accounts_selector.{} entries must be strings", key))
+                })
+                .collect()
+        };
+
+        let accounts = parse_list("accounts")?;
+        let owners = parse_list("owners")?;
+        AccountsSelector::new(&accounts, &owners)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_accounts_selector() {
+        AccountsSelector::new(
+            &["9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin".to_string()],
+            &[],
+        )
+        .unwrap();
+
+        AccountsSelector::new(
+            &[],
+            &["9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin".to_string()],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_base58_pubkey_instead_of_panicking() {
+        assert!(AccountsSelector::new(&["not-valid-base58-!!!".to_string()], &[]).is_err());
+        assert!(AccountsSelector::new(&[], &["not-valid-base58-!!!".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_from_config_value_rejects_bad_shape() {
+        let config: serde_json::Value =
+            serde_json::from_str("{\"accounts_selector\": {\"accounts\": \"not-an-array\"}}")
+                .unwrap();
+        assert!(AccountsSelector::from_config_value(&config).is_err());
+    }
+
+    #[test]
+    fn test_from_config_value_rejects_invalid_pubkey_instead_of_panicking() {
+        let config: serde_json::Value = serde_json::from_str(
+            "{\"accounts_selector\": {\"accounts\": [\"not-valid-base58-!!!\"]}}",
+        )
+        .unwrap();
+        assert!(AccountsSelector::from_config_value(&config).is_err());
+    }
+
+    #[test]
+    fn test_register_unregister_on_demand() {
+        let mut selector = AccountsSelector::default();
+        let account = vec![1u8; 32];
+        assert!(!selector.is_account_selected(&account, &[]));
+
+        assert!(selector.register_account(account.clone()));
+        assert!(selector.is_account_selected(&account, &[]));
+        assert!(selector.is_on_demand(&account));
+
+        assert!(selector.unregister_account(&account));
+        assert!(!selector.is_account_selected(&account, &[]));
+    }
+
+    #[test]
+    fn test_register_unregister_owner_tracked_separately_from_static_owners() {
+        let mut selector = AccountsSelector::default();
+        let owner = vec![2u8; 32];
+        assert!(!selector.is_account_selected(&[], &owner));
+
+        assert!(selector.register_owner(owner.clone()));
+        assert!(selector.is_account_selected(&[], &owner));
+        assert!(selector.on_demand_owners.contains(&owner));
+        assert!(!selector.owners.contains(&owner));
+
+        assert!(selector.unregister_owner(&owner));
+        assert!(!selector.is_account_selected(&[], &owner));
+    }
+}
@@ -1,8 +1,52 @@
-use std::collections::HashSet;
-use std::sync::RwLock;
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
 
+/// Accounts that saw at least one account write.
+///
+/// Needed to catch writes that signal account closure, where lamports=0 and
+/// owner=system-program: such a write no longer matches the selector, but
+/// must still be forwarded since it's closing an account we'd previously
+/// selected.
+///
+/// Accounts registered on demand carry a `last_seen` timestamp so they can
+/// be evicted via `evict_stale` once they go quiet; statically selected
+/// accounts are tracked with `last_seen: None` and are never evicted.
+#[derive(Default)]
 pub struct ActiveAccounts {
-    active_accounts: RwLock<HashSet<[u8; 32]>>,
+    entries: RwLock<HashMap<[u8; 32], Option<Instant>>>,
 }
 
-impl ActiveAccounts {}
+impl ActiveAccounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, pubkey: &[u8]) -> bool {
+        let key: [u8; 32] = pubkey.try_into().expect("pubkey must be 32 bytes");
+        self.entries.read().unwrap().contains_key(&key)
+    }
+
+    /// Record a write to `pubkey`. `on_demand` accounts get their
+    /// `last_seen` timestamp refreshed so they survive `evict_stale` while
+    /// still active.
+    pub fn record_write(&self, pubkey: &[u8], on_demand: bool) {
+        let key: [u8; 32] = pubkey.try_into().expect("pubkey must be 32 bytes");
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(key, if on_demand { Some(Instant::now()) } else { None });
+    }
+
+    /// Drop on-demand entries that haven't seen a write in `ttl`, to bound
+    /// memory growth from accounts registered on demand and then abandoned.
+    /// Statically selected entries (`last_seen: None`) are never evicted.
+    pub fn evict_stale(&self, ttl: Duration) {
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|_, last_seen| match last_seen {
+            Some(seen) => seen.elapsed() < ttl,
+            None => true,
+        });
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    convert::TryFrom,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use log::*;
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use tokio::sync::broadcast;
+use tonic::{Request, Response, Status};
+
+use crate::{
+    accounts_selector::AccountsSelector,
+    active_accounts::ActiveAccounts,
+    admin_proto::{
+        admin_server::Admin, Empty, RegisterAccountRequest, RegisterOwnerRequest,
+        UnregisterAccountRequest, UnregisterOwnerRequest,
+    },
+    geyser_proto::{update::UpdateOneof, AccountWrite, Update},
+};
+
+/// Fetches current on-chain state for a set of accounts right after they're
+/// registered on demand, so subscribers don't have to wait for the next
+/// write to see current state.
+pub trait SnapshotFetcher: Send + Sync {
+    fn fetch_now(&self, pubkeys: Vec<Vec<u8>>);
+}
+
+/// The `SnapshotFetcher` this crate ships: a one-shot `getMultipleAccounts`
+/// call against `SnapshotSourceConfig::rpc_http_url`, broadcast onto the
+/// same channel as live writes. Runs the RPC call on a spawned thread
+/// instead of the caller (`register_account`, itself on the tonic async
+/// runtime) so a slow RPC endpoint can't stall the admin service.
+pub struct RpcSnapshotFetcher {
+    rpc_http_url: String,
+    server_broadcast: broadcast::Sender<Update>,
+}
+
+impl RpcSnapshotFetcher {
+    pub fn new(rpc_http_url: String, server_broadcast: broadcast::Sender<Update>) -> Self {
+        Self {
+            rpc_http_url,
+            server_broadcast,
+        }
+    }
+}
+
+impl SnapshotFetcher for RpcSnapshotFetcher {
+    fn fetch_now(&self, pubkeys: Vec<Vec<u8>>) {
+        let rpc_http_url = self.rpc_http_url.clone();
+        let server_broadcast = self.server_broadcast.clone();
+        std::thread::spawn(move || {
+            let pubkeys: Vec<Pubkey> = pubkeys
+                .iter()
+                .filter_map(|key| Pubkey::try_from(key.as_slice()).ok())
+                .collect();
+            if pubkeys.is_empty() {
+                return;
+            }
+
+            let client = RpcClient::new(rpc_http_url);
+            let accounts = match client.get_multiple_accounts(&pubkeys) {
+                Ok(accounts) => accounts,
+                Err(err) => {
+                    warn!("snapshot fetch failed: {:?}", err);
+                    return;
+                }
+            };
+            for (pubkey, account) in pubkeys.into_iter().zip(accounts) {
+                let Some(account) = account else {
+                    continue;
+                };
+                // Don't care about the error that happens when there are no
+                // receivers, same as the live broadcast path.
+                let _ = server_broadcast.send(Update {
+                    update_oneof: Some(UpdateOneof::AccountWrite(AccountWrite {
+                        pubkey: pubkey.to_bytes().to_vec(),
+                        owner: account.owner.to_bytes().to_vec(),
+                        data: account.data,
+                        lamports: account.lamports,
+                        rent_epoch: account.rent_epoch,
+                        executable: account.executable,
+                        tx_signature: None,
+                        is_startup: false,
+                        slot: 0,
+                        write_version: 0,
+                    })),
+                });
+            }
+        });
+    }
+}
+
+/// A second tonic service, exposed alongside the geyser streaming service,
+/// that lets an operator add/remove tracked accounts and owners without
+/// reloading the plugin.
+pub struct AdminService {
+    accounts_selector: Arc<RwLock<AccountsSelector>>,
+    active_accounts: Arc<ActiveAccounts>,
+    snapshot_fetcher: Option<Arc<dyn SnapshotFetcher>>,
+}
+
+impl AdminService {
+    pub fn new(
+        accounts_selector: Arc<RwLock<AccountsSelector>>,
+        active_accounts: Arc<ActiveAccounts>,
+        snapshot_fetcher: Option<Arc<dyn SnapshotFetcher>>,
+    ) -> Self {
+        Self {
+            accounts_selector,
+            active_accounts,
+            snapshot_fetcher,
+        }
+    }
+
+    /// On a timer, evicts on-demand entries from `active_accounts` that have
+    /// gone quiet for `ttl` and logs how many on-demand accounts the
+    /// selector holds. It does not re-register or otherwise change the
+    /// selector's on-demand set. Runs until the plugin is unloaded and the
+    /// runtime is dropped.
+    pub async fn run_periodic_sweep(
+        accounts_selector: Arc<RwLock<AccountsSelector>>,
+        active_accounts: Arc<ActiveAccounts>,
+        resweep_interval: Duration,
+        ttl: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(resweep_interval).await;
+
+            active_accounts.evict_stale(ttl);
+
+            let on_demand_count = accounts_selector.read().unwrap().on_demand_accounts.len();
+            debug!(
+                "admin resweep: {} on-demand accounts currently tracked",
+                on_demand_count
+            );
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Admin for AdminService {
+    async fn register_account(
+        &self,
+        request: Request<RegisterAccountRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let pubkey = request.into_inner().pubkey;
+        let newly_added = self
+            .accounts_selector
+            .write()
+            .unwrap()
+            .register_account(pubkey.clone());
+
+        if newly_added {
+            info!(
+                "admin: registered account {}",
+                bs58::encode(&pubkey).into_string()
+            );
+            if let Some(fetcher) = &self.snapshot_fetcher {
+                fetcher.fetch_now(vec![pubkey]);
+            }
+        }
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn unregister_account(
+        &self,
+        request: Request<UnregisterAccountRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let pubkey = request.into_inner().pubkey;
+        self.accounts_selector
+            .write()
+            .unwrap()
+            .unregister_account(&pubkey);
+        info!(
+            "admin: unregistered account {}",
+            bs58::encode(&pubkey).into_string()
+        );
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn register_owner(
+        &self,
+        request: Request<RegisterOwnerRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let owner = request.into_inner().owner;
+        self.accounts_selector
+            .write()
+            .unwrap()
+            .register_owner(owner.clone());
+        info!("admin: registered owner {}", bs58::encode(&owner).into_string());
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn unregister_owner(
+        &self,
+        request: Request<UnregisterOwnerRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let owner = request.into_inner().owner;
+        self.accounts_selector
+            .write()
+            .unwrap()
+            .unregister_owner(&owner);
+        info!(
+            "admin: unregistered owner {}",
+            bs58::encode(&owner).into_string()
+        );
+        Ok(Response::new(Empty {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct RecordingFetcher {
+        calls: Arc<Mutex<Vec<Vec<Vec<u8>>>>>,
+    }
+
+    impl SnapshotFetcher for RecordingFetcher {
+        fn fetch_now(&self, pubkeys: Vec<Vec<u8>>) {
+            self.calls.lock().unwrap().push(pubkeys);
+        }
+    }
+
+    fn service_with_fetcher() -> (AdminService, Arc<Mutex<Vec<Vec<Vec<u8>>>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let fetcher: Arc<dyn SnapshotFetcher> = Arc::new(RecordingFetcher {
+            calls: calls.clone(),
+        });
+        let service = AdminService::new(
+            Arc::new(RwLock::new(AccountsSelector::default())),
+            Arc::new(ActiveAccounts::new()),
+            Some(fetcher),
+        );
+        (service, calls)
+    }
+
+    #[tokio::test]
+    async fn register_account_triggers_a_snapshot_fetch_for_a_newly_added_account() {
+        let (service, calls) = service_with_fetcher();
+        let pubkey = vec![1u8; 32];
+
+        service
+            .register_account(Request::new(RegisterAccountRequest {
+                pubkey: pubkey.clone(),
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(calls.lock().unwrap().as_slice(), &[vec![pubkey]]);
+    }
+
+    #[tokio::test]
+    async fn register_account_does_not_refetch_an_already_tracked_account() {
+        let (service, calls) = service_with_fetcher();
+        let pubkey = vec![1u8; 32];
+
+        service
+            .register_account(Request::new(RegisterAccountRequest {
+                pubkey: pubkey.clone(),
+            }))
+            .await
+            .unwrap();
+        service
+            .register_account(Request::new(RegisterAccountRequest { pubkey }))
+            .await
+            .unwrap();
+
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unregister_account_removes_it_from_the_on_demand_set() {
+        let (service, _calls) = service_with_fetcher();
+        let pubkey = vec![1u8; 32];
+        service
+            .register_account(Request::new(RegisterAccountRequest {
+                pubkey: pubkey.clone(),
+            }))
+            .await
+            .unwrap();
+        assert!(service.accounts_selector.read().unwrap().is_on_demand(&pubkey));
+
+        service
+            .unregister_account(Request::new(UnregisterAccountRequest { pubkey: pubkey.clone() }))
+            .await
+            .unwrap();
+
+        assert!(!service.accounts_selector.read().unwrap().is_on_demand(&pubkey));
+    }
+
+    #[tokio::test]
+    async fn register_and_unregister_owner_round_trip() {
+        let (service, _calls) = service_with_fetcher();
+        let owner = vec![2u8; 32];
+
+        service
+            .register_owner(Request::new(RegisterOwnerRequest { owner: owner.clone() }))
+            .await
+            .unwrap();
+        assert!(service
+            .accounts_selector
+            .read()
+            .unwrap()
+            .on_demand_owners
+            .contains(&owner));
+
+        service
+            .unregister_owner(Request::new(UnregisterOwnerRequest { owner: owner.clone() }))
+            .await
+            .unwrap();
+        assert!(!service
+            .accounts_selector
+            .read()
+            .unwrap()
+            .on_demand_owners
+            .contains(&owner));
+    }
+
+    #[tokio::test]
+    async fn run_periodic_sweep_evicts_on_demand_accounts_past_their_ttl() {
+        let accounts_selector = Arc::new(RwLock::new(AccountsSelector::default()));
+        let active_accounts = Arc::new(ActiveAccounts::new());
+        let pubkey = vec![3u8; 32];
+        active_accounts.record_write(&pubkey, true);
+        assert!(active_accounts.contains(&pubkey));
+
+        let sweep = AdminService::run_periodic_sweep(
+            accounts_selector,
+            active_accounts.clone(),
+            Duration::from_millis(5),
+            Duration::from_millis(1),
+        );
+        let _ = tokio::time::timeout(Duration::from_millis(30), sweep).await;
+
+        assert!(!active_accounts.contains(&pubkey));
+    }
+}
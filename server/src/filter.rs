@@ -0,0 +1,439 @@
+use std::collections::HashSet;
+
+use crate::geyser_proto::{
+    slot_update::Status as SlotUpdateStatus,
+    subscribe_request_filter_slots::CommitmentLevel as FilterCommitmentLevel, update::UpdateOneof,
+    AccountWrite, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterMemcmp,
+    Update,
+};
+
+/// Ranks a `SlotUpdate`'s status by finality, so it can be compared against a
+/// subscriber's `commitment_floor`. Higher is more final.
+fn status_rank(status: SlotUpdateStatus) -> u8 {
+    match status {
+        SlotUpdateStatus::Processed => 0,
+        SlotUpdateStatus::Confirmed => 1,
+        SlotUpdateStatus::Rooted => 2,
+    }
+}
+
+/// Ranks a subscriber's requested `CommitmentLevel` the same way, so the two
+/// can be compared directly. `Rooted` (the slot status a fully confirmed
+/// slot eventually reaches) is this tree's equivalent of `Finalized`.
+fn commitment_rank(level: FilterCommitmentLevel) -> u8 {
+    match level {
+        FilterCommitmentLevel::Processed => 0,
+        FilterCommitmentLevel::Confirmed => 1,
+        FilterCommitmentLevel::Finalized => 2,
+    }
+}
+
+/// A single `memcmp { offset, bytes }` predicate: the account data must match
+/// `bytes` exactly at `offset`.
+#[derive(Debug, Clone)]
+pub(crate) struct Memcmp {
+    offset: usize,
+    bytes: Vec<u8>,
+}
+
+impl Memcmp {
+    fn matches(&self, data: &[u8]) -> bool {
+        if self.bytes.is_empty() {
+            return true;
+        }
+        match data.get(self.offset..self.offset + self.bytes.len()) {
+            Some(slice) => slice == self.bytes.as_slice(),
+            None => false,
+        }
+    }
+}
+
+/// A compiled, named account filter. Within a filter, every predicate
+/// category that was specified must match (conjunction); an update passes
+/// the whole filter set if it matches any one named filter (disjunction).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AccountFilter {
+    accounts: HashSet<Vec<u8>>,
+    owners: HashSet<Vec<u8>>,
+    data_size: Option<u64>,
+    memcmp: Vec<Memcmp>,
+}
+
+impl AccountFilter {
+    fn from_proto(filter: &SubscribeRequestFilterAccounts) -> Self {
+        AccountFilter {
+            accounts: filter.account.iter().cloned().collect(),
+            owners: filter.owner.iter().cloned().collect(),
+            data_size: filter.data_size,
+            memcmp: filter
+                .memcmp
+                .iter()
+                .map(|m| Memcmp {
+                    offset: m.offset as usize,
+                    bytes: m.bytes.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn matches(&self, write: &AccountWrite) -> bool {
+        if !self.accounts.is_empty() && !self.accounts.contains(&write.pubkey) {
+            return false;
+        }
+        if !self.owners.is_empty() && !self.owners.contains(&write.owner) {
+            return false;
+        }
+        if let Some(data_size) = self.data_size {
+            if write.data.len() as u64 != data_size {
+                return false;
+            }
+        }
+        if !self.memcmp.iter().all(|m| m.matches(&write.data)) {
+            return false;
+        }
+        true
+    }
+}
+
+/// Whether slot updates are forwarded to this subscriber, and at what
+/// minimum commitment.
+#[derive(Debug, Clone)]
+pub(crate) struct SlotsFilter {
+    enabled: bool,
+    commitment_floor: Option<FilterCommitmentLevel>,
+}
+
+impl Default for SlotsFilter {
+    fn default() -> Self {
+        SlotsFilter {
+            enabled: true,
+            commitment_floor: None,
+        }
+    }
+}
+
+/// How much of an account's `data` a subscriber wants to receive: the whole
+/// thing (default), a byte range (to bound bandwidth on large accounts), or
+/// none at all (pubkey + metadata only, for clients that just want change
+/// notifications).
+#[derive(Debug, Clone)]
+pub(crate) enum DataMode {
+    Full,
+    Slice { offset: usize, length: usize },
+    None,
+}
+
+impl Default for DataMode {
+    fn default() -> Self {
+        DataMode::Full
+    }
+}
+
+impl DataMode {
+    fn apply(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DataMode::Full => data.to_vec(),
+            DataMode::None => Vec::new(),
+            DataMode::Slice { offset, length } => data
+                .get(*offset..*offset + *length)
+                .unwrap_or_default()
+                .to_vec(),
+        }
+    }
+}
+
+/// The compiled set of filters for one subscriber connection, evaluated in
+/// the forwarding task before every `tx.send`. An empty `accounts` list
+/// matches everything, preserving old match-all behavior for clients that
+/// don't send any filters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Filters {
+    accounts: Vec<AccountFilter>,
+    slots: SlotsFilter,
+    data_mode: DataMode,
+}
+
+impl Filters {
+    pub(crate) fn from_request(request: &SubscribeRequest) -> Self {
+        let data_mode = if request.no_data {
+            DataMode::None
+        } else if let Some(slice) = &request.data_slice {
+            DataMode::Slice {
+                offset: slice.offset as usize,
+                length: slice.length as usize,
+            }
+        } else {
+            DataMode::Full
+        };
+
+        let slots = request
+            .slots_filter
+            .as_ref()
+            .map(|s| SlotsFilter {
+                enabled: s.enabled,
+                commitment_floor: s.commitment_level(),
+            })
+            .unwrap_or_default();
+
+        if request.account_filters.is_empty() {
+            return Filters {
+                slots,
+                data_mode,
+                ..Filters::default()
+            };
+        }
+        Filters {
+            accounts: request
+                .account_filters
+                .values()
+                .map(AccountFilter::from_proto)
+                .collect(),
+            slots,
+            data_mode,
+        }
+    }
+
+    pub(crate) fn matches_account(&self, write: &AccountWrite) -> bool {
+        self.accounts.is_empty() || self.accounts.iter().any(|f| f.matches(write))
+    }
+
+    pub(crate) fn wants_slots(&self) -> bool {
+        self.slots.enabled
+    }
+
+    /// Whether a `SlotUpdate` at `status` should be forwarded: the
+    /// subscriber must both want slot updates at all and, if it set a
+    /// `commitment_floor`, the update must be at least that final. A
+    /// subscriber that asked for Rooted-only updates otherwise got the same
+    /// unfiltered stream as one with no floor set.
+    pub(crate) fn wants_slot_update(&self, status: SlotUpdateStatus) -> bool {
+        if !self.slots.enabled {
+            return false;
+        }
+        match self.slots.commitment_floor {
+            Some(floor) => status_rank(status) >= commitment_rank(floor),
+            None => true,
+        }
+    }
+
+    /// Apply this subscriber's `data_slice`/`no_data` preference, returning
+    /// the (possibly truncated or emptied) account data to forward.
+    pub(crate) fn apply_data_mode(&self, data: &[u8]) -> Vec<u8> {
+        self.data_mode.apply(data)
+    }
+
+    /// Evaluates `update` against this subscriber's filters and, if it
+    /// should be forwarded, returns a copy with `data_mode` applied. Used by
+    /// both the live broadcast path and lag backfill, so a subscriber that
+    /// falls behind can't receive unfiltered, untruncated updates just
+    /// because they came out of the shared replay buffer instead of the
+    /// live channel.
+    pub(crate) fn apply(&self, update: &Update) -> Option<Update> {
+        let mut update = update.clone();
+        let passes = match &mut update.update_oneof {
+            Some(UpdateOneof::AccountWrite(write)) => {
+                let matches = self.matches_account(write);
+                if matches {
+                    write.data = self.apply_data_mode(&write.data);
+                }
+                matches
+            }
+            Some(UpdateOneof::SlotUpdate(slot_update)) => {
+                self.wants_slot_update(slot_update.status())
+            }
+            _ => true,
+        };
+        passes.then_some(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geyser_proto::SlotUpdate;
+
+    fn account_write(pubkey: &[u8], owner: &[u8], data: &[u8]) -> AccountWrite {
+        AccountWrite {
+            pubkey: pubkey.to_vec(),
+            owner: owner.to_vec(),
+            data: data.to_vec(),
+            lamports: 0,
+            rent_epoch: 0,
+            executable: false,
+            tx_signature: None,
+            is_startup: false,
+            slot: 0,
+            write_version: 0,
+        }
+    }
+
+    #[test]
+    fn empty_filters_matches_everything() {
+        let filters = Filters::default();
+        let write = account_write(b"pubkey", b"owner", b"data");
+        assert!(filters.matches_account(&write));
+    }
+
+    #[test]
+    fn named_filter_with_nothing_set_matches_everything() {
+        let filters = Filters {
+            accounts: vec![AccountFilter::default()],
+            ..Filters::default()
+        };
+        let write = account_write(b"pubkey", b"owner", b"data");
+        // An AccountFilter with nothing set matches trivially (every
+        // per-field check is skipped when its HashSet is empty), but
+        // Filters::matches_account only reaches that branch when
+        // `self.accounts` itself is non-empty, which this guards against
+        // collapsing into the "no filters configured" match-all case above.
+        assert!(filters.matches_account(&write));
+    }
+
+    #[test]
+    fn owner_filter_rejects_non_matching_owner() {
+        let filters = Filters {
+            accounts: vec![AccountFilter {
+                owners: [b"owner-a".to_vec()].into_iter().collect(),
+                ..AccountFilter::default()
+            }],
+            ..Filters::default()
+        };
+        assert!(filters.matches_account(&account_write(b"pubkey", b"owner-a", b"data")));
+        assert!(!filters.matches_account(&account_write(b"pubkey", b"owner-b", b"data")));
+    }
+
+    #[test]
+    fn memcmp_with_empty_bytes_matches_any_data_including_empty() {
+        let memcmp = Memcmp {
+            offset: 4,
+            bytes: Vec::new(),
+        };
+        assert!(memcmp.matches(b"anything"));
+        assert!(memcmp.matches(b""));
+    }
+
+    #[test]
+    fn memcmp_rejects_data_shorter_than_offset_plus_bytes() {
+        let memcmp = Memcmp {
+            offset: 4,
+            bytes: vec![1, 2, 3],
+        };
+        assert!(!memcmp.matches(&[0; 4]));
+    }
+
+    #[test]
+    fn data_mode_full_passes_data_through() {
+        assert_eq!(DataMode::Full.apply(b"hello"), b"hello".to_vec());
+    }
+
+    #[test]
+    fn data_mode_none_returns_empty() {
+        assert_eq!(DataMode::None.apply(b"hello"), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn data_mode_slice_out_of_bounds_returns_empty_instead_of_panicking() {
+        let slice = DataMode::Slice { offset: 10, length: 5 };
+        assert_eq!(slice.apply(b"short"), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn apply_drops_non_matching_account_write() {
+        let filters = Filters {
+            accounts: vec![AccountFilter {
+                owners: [b"owner-a".to_vec()].into_iter().collect(),
+                ..AccountFilter::default()
+            }],
+            ..Filters::default()
+        };
+        let update = Update {
+            update_oneof: Some(UpdateOneof::AccountWrite(account_write(
+                b"pubkey", b"owner-b", b"data",
+            ))),
+        };
+        assert!(filters.apply(&update).is_none());
+    }
+
+    #[test]
+    fn apply_truncates_matching_account_write_per_data_mode() {
+        let filters = Filters {
+            data_mode: DataMode::Slice { offset: 0, length: 2 },
+            ..Filters::default()
+        };
+        let update = Update {
+            update_oneof: Some(UpdateOneof::AccountWrite(account_write(
+                b"pubkey", b"owner", b"hello",
+            ))),
+        };
+        let filtered = filters.apply(&update).expect("empty filter set matches everything");
+        match filtered.update_oneof {
+            Some(UpdateOneof::AccountWrite(write)) => assert_eq!(write.data, b"he".to_vec()),
+            _ => panic!("expected an AccountWrite"),
+        }
+    }
+
+    #[test]
+    fn apply_drops_slot_update_when_subscriber_did_not_ask_for_slots() {
+        let filters = Filters {
+            slots: SlotsFilter {
+                enabled: false,
+                commitment_floor: None,
+            },
+            ..Filters::default()
+        };
+        let update = Update {
+            update_oneof: Some(UpdateOneof::SlotUpdate(SlotUpdate {
+                slot: 1,
+                parent: None,
+                status: 0,
+            })),
+        };
+        assert!(filters.apply(&update).is_none());
+    }
+
+    #[test]
+    fn apply_drops_slot_update_below_the_requested_commitment_floor() {
+        let filters = Filters {
+            slots: SlotsFilter {
+                enabled: true,
+                commitment_floor: Some(FilterCommitmentLevel::Finalized),
+            },
+            ..Filters::default()
+        };
+        let processed = Update {
+            update_oneof: Some(UpdateOneof::SlotUpdate(SlotUpdate {
+                slot: 1,
+                parent: None,
+                status: SlotUpdateStatus::Processed as i32,
+            })),
+        };
+        let rooted = Update {
+            update_oneof: Some(UpdateOneof::SlotUpdate(SlotUpdate {
+                slot: 1,
+                parent: None,
+                status: SlotUpdateStatus::Rooted as i32,
+            })),
+        };
+        assert!(filters.apply(&processed).is_none());
+        assert!(filters.apply(&rooted).is_some());
+    }
+
+    #[test]
+    fn apply_forwards_every_slot_update_when_no_commitment_floor_is_set() {
+        let filters = Filters {
+            slots: SlotsFilter {
+                enabled: true,
+                commitment_floor: None,
+            },
+            ..Filters::default()
+        };
+        let update = Update {
+            update_oneof: Some(UpdateOneof::SlotUpdate(SlotUpdate {
+                slot: 1,
+                parent: None,
+                status: SlotUpdateStatus::Processed as i32,
+            })),
+        };
+        assert!(filters.apply(&update).is_some());
+    }
+}
@@ -1,12 +1,12 @@
 use std::{
-    collections::HashSet,
-    convert::TryInto,
     fs::File,
     io::Read,
+    path::PathBuf,
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc, RwLock, RwLockReadGuard,
+        Arc, RwLock,
     },
+    time::Duration,
 };
 
 use bs58;
@@ -17,22 +17,26 @@ use solana_geyser_plugin_interface::geyser_plugin_interface::{
     GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions, Result as PluginResult, SlotStatus,
 };
 use tokio::sync::{broadcast, mpsc};
-use tonic::transport::Server;
+use tonic::{codec::CompressionEncoding, transport::Server};
 
 use crate::{
     accounts_selector::AccountsSelector,
     active_accounts::ActiveAccounts,
+    admin::{AdminService, RpcSnapshotFetcher, SnapshotFetcher},
+    admin_proto::admin_server::AdminServer,
     geyser_proto::{
         slot_update::Status as SlotUpdateStatus, update::UpdateOneof, AccountWrite, Ping,
         SlotUpdate, SubscribeRequest, SubscribeResponse, Update,
     },
+    server as geyser_service,
+    sink::Router,
 };
 
 pub struct PluginData {
     runtime: Option<tokio::runtime::Runtime>,
     server_broadcast: broadcast::Sender<Update>,
     server_exit_sender: Option<broadcast::Sender<()>>,
-    accounts_selector: AccountsSelector,
+    accounts_selector: Arc<RwLock<AccountsSelector>>,
 
     /// Largest slot that an account write was processed for
     highest_write_slot: Arc<AtomicU64>,
@@ -41,7 +45,11 @@ pub struct PluginData {
     ///
     /// Needed to catch writes that signal account closure, where
     /// lamports=0 and owner=system-program.
-    active_accounts: ActiveAccounts,
+    active_accounts: Arc<ActiveAccounts>,
+
+    /// Dispatches account writes to any custom sinks configured for their
+    /// pubkey, in addition to the gRPC broadcast.
+    router: Router,
 }
 
 #[derive(Default)]
@@ -56,10 +64,63 @@ impl std::fmt::Debug for Plugin {
     }
 }
 
+/// Default bind address for the admin service when a deployed config
+/// predates it: binds to an ephemeral local port rather than failing to
+/// deserialize, since there's no one bind address that's right for every
+/// operator.
+fn default_admin_bind_address() -> String {
+    "127.0.0.1:0".to_string()
+}
+
+fn default_admin_resweep_interval_secs() -> u64 {
+    60
+}
+
+fn default_admin_account_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_hot_reload_poll_interval_secs() -> u64 {
+    5
+}
+
+fn default_sink_metrics_log_interval_secs() -> u64 {
+    60
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct PluginConfig {
     pub bind_address: String,
     pub service_config: geyser_service::ServiceConfig,
+
+    /// Bind address for the admin service that lets an operator
+    /// register/unregister on-demand accounts and owners at runtime.
+    #[serde(default = "default_admin_bind_address")]
+    pub admin_bind_address: String,
+
+    /// How often the admin service evicts on-demand entries that haven't
+    /// seen a write in `admin_account_ttl_secs`.
+    #[serde(default = "default_admin_resweep_interval_secs")]
+    pub admin_resweep_interval_secs: u64,
+
+    /// TTL for on-demand accounts that have seen no writes.
+    #[serde(default = "default_admin_account_ttl_secs")]
+    pub admin_account_ttl_secs: u64,
+
+    /// How often to poll the config file for accounts_selector edits.
+    #[serde(default = "default_hot_reload_poll_interval_secs")]
+    pub hot_reload_poll_interval_secs: u64,
+
+    /// How often to log sink dispatch success/failure/timeout counts.
+    #[serde(default = "default_sink_metrics_log_interval_secs")]
+    pub sink_metrics_log_interval_secs: u64,
+
+    /// RPC endpoint used to snapshot an account's current state the moment
+    /// it's registered on demand through the admin service. Left unset, a
+    /// newly registered account only starts streaming future writes. Not
+    /// required, so configs written before this existed keep deserializing.
+    #[serde(default)]
+    pub rpc_http_url: Option<String>,
 }
 
 impl PluginData {
@@ -70,11 +131,11 @@ impl PluginData {
         });
     }
 
-    pub(crate) fn accounts_selector(&self) -> &AccountsSelector {
+    pub(crate) fn accounts_selector(&self) -> &Arc<RwLock<AccountsSelector>> {
         &self.accounts_selector
     }
 
-    pub(crate) fn active_accounts(&self) -> &ActiveAccounts {
+    pub(crate) fn active_accounts(&self) -> &Arc<ActiveAccounts> {
         &self.active_accounts
     }
 }
@@ -97,7 +158,12 @@ impl GeyserPlugin for Plugin {
         file.read_to_string(&mut contents)?;
 
         let result: serde_json::Value = serde_json::from_str(&contents).unwrap();
-        let accounts_selector = Self::create_accounts_selector_from_config(&result);
+        let accounts_selector =
+            AccountsSelector::from_config_value(&result).map_err(|err| {
+                GeyserPluginError::ConfigFileReadError {
+                    msg: format!("Invalid accounts_selector: {}", err),
+                }
+            })?;
 
         let config: PluginConfig = serde_json::from_str(&contents).map_err(|err| {
             GeyserPluginError::ConfigFileReadError {
@@ -115,15 +181,34 @@ impl GeyserPlugin for Plugin {
                 .map_err(|err| GeyserPluginError::ConfigFileReadError {
                     msg: format!("Error parsing the bind_address {:?}", err),
                 })?;
+        let admin_addr =
+            config
+                .admin_bind_address
+                .parse()
+                .map_err(|err| GeyserPluginError::ConfigFileReadError {
+                    msg: format!("Error parsing the admin_bind_address {:?}", err),
+                })?;
+
+        let accounts_selector = Arc::new(RwLock::new(accounts_selector));
+        let active_accounts = Arc::new(ActiveAccounts::new());
 
         let highest_write_slot = Arc::new(AtomicU64::new(0));
         let service =
             geyser_service::Service::new(config.service_config, highest_write_slot.clone());
         let (server_exit_sender, mut server_exit_receiver) = broadcast::channel::<()>(1);
         let server_broadcast = service.sender.clone();
+        let recent_updates = service.recent_updates.clone();
+        let lag_buffer_size = service.lag_buffer_size;
 
-        let server = geyser_proto::accounts_db_server::AccountsDbServer::new(service);
+        let server = geyser_proto::accounts_db_server::AccountsDbServer::new(service)
+            .send_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Gzip);
         let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.spawn(geyser_service::Service::run_backfill_recorder(
+            server_broadcast.clone(),
+            recent_updates,
+            lag_buffer_size,
+        ));
         runtime.spawn(Server::builder().add_service(server).serve_with_shutdown(
             addr,
             async move {
@@ -146,13 +231,60 @@ impl GeyserPlugin for Plugin {
             }
         });
 
+        // Only snapshot on registration if the operator configured an RPC
+        // endpoint to fetch from; otherwise on-demand registration starts
+        // streaming future writes only, same as before this existed.
+        let snapshot_fetcher: Option<Arc<dyn SnapshotFetcher>> = config
+            .rpc_http_url
+            .clone()
+            .map(|url| Arc::new(RpcSnapshotFetcher::new(url, server_broadcast.clone())) as _);
+        let admin_service = AdminServer::new(AdminService::new(
+            accounts_selector.clone(),
+            active_accounts.clone(),
+            snapshot_fetcher,
+        ));
+        let mut admin_exit_receiver = server_exit_sender.subscribe();
+        runtime.spawn(
+            Server::builder()
+                .add_service(admin_service)
+                .serve_with_shutdown(admin_addr, async move {
+                    let _ = admin_exit_receiver.recv().await;
+                }),
+        );
+
+        let resweep_accounts_selector = accounts_selector.clone();
+        let resweep_active_accounts = active_accounts.clone();
+        let resweep_interval = Duration::from_secs(config.admin_resweep_interval_secs);
+        let account_ttl = Duration::from_secs(config.admin_account_ttl_secs);
+        runtime.spawn(AdminService::run_periodic_sweep(
+            resweep_accounts_selector,
+            resweep_active_accounts,
+            resweep_interval,
+            account_ttl,
+        ));
+
+        runtime.spawn(crate::hot_reload::watch_accounts_selector(
+            PathBuf::from(config_file),
+            accounts_selector.clone(),
+            Duration::from_secs(config.hot_reload_poll_interval_secs),
+        ));
+
+        // No custom sinks are configured yet; routes get added here as the
+        // config grows a way to declare them (see sink.rs).
+        let router = Router::new(vec![]);
+        runtime.spawn(Router::run_periodic_metrics_log(
+            router.metrics(),
+            Duration::from_secs(config.sink_metrics_log_interval_secs),
+        ));
+
         self.data = Some(PluginData {
             runtime: Some(runtime),
             server_broadcast,
             server_exit_sender: Some(server_exit_sender),
             accounts_selector,
             highest_write_slot,
-            active_accounts: RwLock::new(HashSet::new()),
+            active_accounts,
+            router,
         });
 
         Ok(())
@@ -181,17 +313,29 @@ impl GeyserPlugin for Plugin {
         is_startup: bool,
     ) -> PluginResult<()> {
         let data = self.data.as_ref().expect("plugin must be initialized");
-        let (pubkey, owner, write_version, maybe_signature) = match account {
-            ReplicaAccountInfoVersions::V0_0_1(account) => {
-                (account.pubkey, account.owner, account.write_version, None)
-            }
-            ReplicaAccountInfoVersions::V0_0_2(account) => (
-                account.pubkey,
-                account.owner,
-                account.write_version,
-                account.txn_signature,
-            ),
-        };
+        let (pubkey, owner, account_data, lamports, rent_epoch, executable, write_version, maybe_signature) =
+            match account {
+                ReplicaAccountInfoVersions::V0_0_1(account) => (
+                    account.pubkey,
+                    account.owner,
+                    account.data,
+                    account.lamports,
+                    account.rent_epoch,
+                    account.executable,
+                    account.write_version,
+                    None,
+                ),
+                ReplicaAccountInfoVersions::V0_0_2(account) => (
+                    account.pubkey,
+                    account.owner,
+                    account.data,
+                    account.lamports,
+                    account.rent_epoch,
+                    account.executable,
+                    account.write_version,
+                    account.txn_signature,
+                ),
+            };
 
         if pubkey.len() != 32 {
             error!(
@@ -203,20 +347,21 @@ impl GeyserPlugin for Plugin {
 
         // Select only accounts configured to look at, plus writes to accounts
         // that were previously selected (to catch closures and account reuse)
-        let is_selected = data.accounts_selector.is_account_selected(pubkey, owner);
-        let previously_selected = {
-            let read = data.active_accounts.read().unwrap();
-            read.contains(&pubkey[0..32])
+        let (is_selected, is_on_demand) = {
+            let selector = data.accounts_selector.read().unwrap();
+            (
+                selector.is_account_selected(pubkey, owner),
+                selector.is_on_demand(pubkey),
+            )
         };
+        let previously_selected = data.active_accounts.contains(pubkey);
         if !is_selected && !previously_selected {
             return Ok(());
         }
 
-        // If the account is newly selected, add it
-        if !previously_selected {
-            let mut write = data.active_accounts.write().unwrap();
-            write.insert(pubkey.try_into().unwrap());
-        }
+        // Track the write so closures/reuse are still caught later, and so
+        // on-demand accounts can be evicted once they go quiet.
+        data.active_accounts.record_write(pubkey, is_on_demand);
 
         data.highest_write_slot.fetch_max(slot, Ordering::SeqCst);
 
@@ -227,13 +372,21 @@ impl GeyserPlugin for Plugin {
             slot,
         );
 
-        data.broadcast(UpdateOneof::AccountWrite(AccountWrite {
+        let account_write = AccountWrite {
             pubkey: pubkey.to_vec(),
+            owner: owner.to_vec(),
+            data: account_data.to_vec(),
+            lamports,
+            rent_epoch,
+            executable,
             tx_signature: maybe_signature.map(|sig| sig.to_string()),
             is_startup,
             slot,
             write_version,
-        }));
+        };
+
+        data.router.dispatch(&account_write);
+        data.broadcast(UpdateOneof::AccountWrite(account_write));
 
         Ok(())
     }
@@ -266,40 +419,6 @@ impl GeyserPlugin for Plugin {
     }
 }
 
-impl Plugin {
-    fn create_accounts_selector_from_config(config: &serde_json::Value) -> AccountsSelector {
-        let accounts_selector = &config["accounts_selector"];
-
-        if accounts_selector.is_null() {
-            AccountsSelector::default()
-        } else {
-            let accounts = &accounts_selector["accounts"];
-            let accounts: Vec<String> = if accounts.is_array() {
-                accounts
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|val| val.as_str().unwrap().to_string())
-                    .collect()
-            } else {
-                Vec::default()
-            };
-            let owners = &accounts_selector["owners"];
-            let owners: Vec<String> = if owners.is_array() {
-                owners
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|val| val.as_str().unwrap().to_string())
-                    .collect()
-            } else {
-                Vec::default()
-            };
-            AccountsSelector::new(&accounts, &owners)
-        }
-    }
-}
-
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
 /// # Safety
@@ -324,6 +443,6 @@ pub(crate) mod tests {
         }}";
 
         let config: serde_json::Value = serde_json::from_str(config).unwrap();
-        Plugin::create_accounts_selector_from_config(&config);
+        AccountsSelector::from_config_value(&config).unwrap();
     }
 }
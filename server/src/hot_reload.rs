@@ -0,0 +1,149 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use log::*;
+
+use crate::accounts_selector::AccountsSelector;
+
+/// Watches `config_file` for changes and swaps the live `accounts_selector`
+/// when its `accounts_selector` block changes, so operators can widen or
+/// narrow the stream without a full validator plugin reload.
+///
+/// Polls the file's mtime rather than using inotify, since that's enough to
+/// catch edits without pulling in a filesystem-watching dependency. A bad
+/// edit is logged and ignored, keeping the previously loaded selector live.
+pub async fn watch_accounts_selector(
+    config_file: PathBuf,
+    accounts_selector: Arc<RwLock<AccountsSelector>>,
+    poll_interval: Duration,
+) {
+    let mut last_modified = fs::metadata(&config_file).and_then(|m| m.modified()).ok();
+
+    loop {
+        tokio::time::sleep(poll_interval).await;
+
+        let modified = match fs::metadata(&config_file).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(err) => {
+                warn!(
+                    "hot-reload: couldn't stat config file {:?}: {:?}",
+                    config_file, err
+                );
+                continue;
+            }
+        };
+        if Some(modified) == last_modified {
+            continue;
+        }
+        last_modified = Some(modified);
+
+        let contents = match fs::read_to_string(&config_file) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!(
+                    "hot-reload: couldn't read config file {:?}: {:?}",
+                    config_file, err
+                );
+                continue;
+            }
+        };
+        let new_selector = match parse_accounts_selector(&contents) {
+            Ok(selector) => selector,
+            Err(err) => {
+                warn!(
+                    "hot-reload: invalid accounts_selector ({}), keeping old one",
+                    err
+                );
+                continue;
+            }
+        };
+
+        let mut current = accounts_selector.write().unwrap();
+        log_diff(&current, &new_selector);
+        *current = carry_forward_on_demand(&current, new_selector);
+    }
+}
+
+/// Parses `contents` (the full config file) into a fresh `AccountsSelector`,
+/// failing on bad JSON or an invalid `accounts_selector` block (including a
+/// malformed base58 pubkey) instead of panicking -- this runs on every
+/// hot-reload tick, so a panic here would permanently kill the spawned
+/// polling task and silently stop picking up edits, even valid corrections
+/// of the bad one.
+fn parse_accounts_selector(contents: &str) -> Result<AccountsSelector, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(contents).map_err(|err| format!("not valid JSON: {:?}", err))?;
+    AccountsSelector::from_config_value(&value)
+}
+
+/// `AccountsSelector::from_config_value` only ever produces empty
+/// `on_demand_accounts`/`on_demand_owners`: those are registered at runtime
+/// through the admin service, not the config file, so copy the live ones
+/// into the freshly parsed selector instead of letting a reload silently
+/// drop them.
+fn carry_forward_on_demand(current: &AccountsSelector, mut new_selector: AccountsSelector) -> AccountsSelector {
+    new_selector.on_demand_accounts = current.on_demand_accounts.clone();
+    new_selector.on_demand_owners = current.on_demand_owners.clone();
+    new_selector
+}
+
+fn log_diff(old: &AccountsSelector, new: &AccountsSelector) {
+    let added: Vec<_> = new
+        .accounts
+        .difference(&old.accounts)
+        .map(|key| bs58::encode(key).into_string())
+        .collect();
+    let removed: Vec<_> = old
+        .accounts
+        .difference(&new.accounts)
+        .map(|key| bs58::encode(key).into_string())
+        .collect();
+    if added.is_empty() && removed.is_empty() {
+        return;
+    }
+    info!(
+        "hot-reload: accounts_selector changed, added={:?} removed={:?}",
+        added, removed
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accounts_selector_rejects_malformed_pubkey_instead_of_panicking() {
+        let contents = r#"{"accounts_selector": {"accounts": ["not-valid-base58-!!!"]}}"#;
+        assert!(parse_accounts_selector(contents).is_err());
+    }
+
+    #[test]
+    fn parse_accounts_selector_rejects_invalid_json() {
+        assert!(parse_accounts_selector("not json").is_err());
+    }
+
+    #[test]
+    fn parse_accounts_selector_accepts_a_valid_config() {
+        let contents = r#"{"accounts_selector": {"accounts": ["9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"]}}"#;
+        assert!(parse_accounts_selector(contents).is_ok());
+    }
+
+    #[test]
+    fn carry_forward_on_demand_preserves_runtime_registered_accounts_and_owners() {
+        let mut current = AccountsSelector::default();
+        let on_demand_account = vec![1u8; 32];
+        let on_demand_owner = vec![2u8; 32];
+        current.register_account(on_demand_account.clone());
+        current.register_owner(on_demand_owner.clone());
+
+        let new_from_config = AccountsSelector::default();
+        let merged = carry_forward_on_demand(&current, new_from_config);
+
+        assert!(merged.is_on_demand(&on_demand_account));
+        assert!(merged.on_demand_owners.contains(&on_demand_owner));
+    }
+}
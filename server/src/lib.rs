@@ -7,13 +7,22 @@ use crate::geyser_plugin_grpc::PluginData;
 
 pub mod accounts_selector;
 pub mod active_accounts;
+pub mod admin;
+pub(crate) mod filter;
 pub mod geyser_plugin_grpc;
+pub mod hot_reload;
+pub mod metrics;
 pub mod server;
+pub mod sink;
 
 pub(crate) mod geyser_proto {
     tonic::include_proto!("geyser");
 }
 
+pub(crate) mod admin_proto {
+    tonic::include_proto!("admin");
+}
+
 pub(crate) fn maybe_new_account_write(pubkey: Pubkey, data: &PluginData) -> Option<AccountWrite> {
     if pubkey.len() != 32 {
         error!(
@@ -1,6 +1,9 @@
-use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    mpsc, Arc,
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
 };
 
 use log::*;
@@ -8,12 +11,34 @@ use tokio::sync::broadcast;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Code, Request, Response, Status};
 
-use crate::geyser_proto::geyser_server::Geyser;
+use crate::{
+    filter::Filters,
+    geyser_proto::{
+        geyser_server::Geyser, update::UpdateOneof, Lagged, SubscribeRequest, SubscribeResponse,
+        Update,
+    },
+};
+
+fn default_lag_buffer_size() -> usize {
+    256
+}
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServiceConfig {
     broadcast_buffer_size: usize,
     subscriber_buffer_size: usize,
+
+    /// Number of recent broadcast updates kept around so a subscriber that
+    /// falls behind can be backfilled instead of disconnected.
+    #[serde(default = "default_lag_buffer_size")]
+    lag_buffer_size: usize,
+
+    /// When a subscriber lags, replay from the buffer instead of just
+    /// telling it how much it missed. Defaults to false, in which case a
+    /// lagging subscriber gets only the `Lagged` notice and keeps receiving
+    /// from the live channel.
+    #[serde(default)]
+    backfill_on_lag: bool,
 }
 
 #[derive(Debug)]
@@ -21,15 +46,47 @@ pub struct Service {
     pub sender: broadcast::Sender<Update>,
     pub config: ServiceConfig,
     pub highest_write_slot: Arc<AtomicU64>,
+    pub recent_updates: Arc<Mutex<VecDeque<Update>>>,
+    pub lag_buffer_size: usize,
 }
 
 impl Service {
     pub fn new(config: ServiceConfig, highest_write_slot: Arc<AtomicU64>) -> Self {
         let (tx, _) = broadcast::channel(config.broadcast_buffer_size);
+        let lag_buffer_size = config.lag_buffer_size.max(1);
+        let recent_updates = Arc::new(Mutex::new(VecDeque::with_capacity(lag_buffer_size)));
         Self {
             sender: tx,
             config,
             highest_write_slot,
+            recent_updates,
+            lag_buffer_size,
+        }
+    }
+
+    /// Keeps `recent_updates` filled with the last `lag_buffer_size`
+    /// broadcast updates, so subscribers that fall behind can be backfilled
+    /// instead of disconnected. Runs for the lifetime of the plugin.
+    pub async fn run_backfill_recorder(
+        sender: broadcast::Sender<Update>,
+        recent_updates: Arc<Mutex<VecDeque<Update>>>,
+        capacity: usize,
+    ) {
+        let mut rx = sender.subscribe();
+        loop {
+            let update = match rx.recv().await {
+                Ok(update) => update,
+                // Falling behind here just means a smaller replay buffer on
+                // the next lag event, not a dropped subscriber -- resync
+                // and keep going.
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let mut buffer = recent_updates.lock().unwrap();
+            if buffer.len() >= capacity.max(1) {
+                buffer.pop_front();
+            }
+            buffer.push_back(update);
         }
     }
 }
@@ -40,15 +97,19 @@ impl Geyser for Service {
 
     async fn subscribe(
         &self,
-        _request: Request<SubscribeRequest>,
+        request: Request<SubscribeRequest>,
     ) -> Result<Response<Self::SubscribeStream>, Status> {
         info!("new subscriber");
+        let filters = Filters::from_request(request.get_ref());
         let (tx, rx) = mpsc::channel(self.config.subscriber_buffer_size);
         let mut broadcast_rx = self.sender.subscribe();
+        let recent_updates = self.recent_updates.clone();
+        let backfill_on_lag = self.config.backfill_on_lag;
+        let highest_write_slot = self.highest_write_slot.clone();
 
         tx.send(Ok(Update {
             update_oneof: Some(UpdateOneof::SubscribeResponse(SubscribeResponse {
-                highest_write_slot: self.highest_write_slot.load(Ordering::SeqCst),
+                highest_write_slot: highest_write_slot.load(Ordering::SeqCst),
             })),
         }))
         .await
@@ -57,15 +118,56 @@ impl Geyser for Service {
         tokio::spawn(async move {
             let mut exit = false;
             while !exit {
-                let fwd = broadcast_rx.recv().await.map_err(|err| {
-                    // Note: If we can't keep up pulling from the broadcast
-                    // channel here, there'll be a Lagged error, and we'll
-                    // close the connection because data was lost.
-                    warn!("error while receiving message to be broadcast: {:?}", err);
-                    exit = true;
-                    Status::new(Code::Internal, err.to_string())
-                });
-                if let Err(_err) = tx.send(fwd).await {
+                let update = match broadcast_rx.recv().await {
+                    Ok(update) => update,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Rather than closing the connection on the first
+                        // Lagged error, tell the subscriber how much it
+                        // missed and, if configured to, replay what's still
+                        // in the buffer instead of losing the stream.
+                        warn!("subscriber lagged, skipped {} updates", skipped);
+                        let resume_slot = highest_write_slot.load(Ordering::SeqCst);
+                        if tx
+                            .send(Ok(Update {
+                                update_oneof: Some(UpdateOneof::Lagged(Lagged {
+                                    skipped,
+                                    resume_slot,
+                                })),
+                            }))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                        if backfill_on_lag {
+                            // Apply this subscriber's filters/data_mode to
+                            // each backfilled update too: the buffer is
+                            // shared across all subscribers unfiltered, so
+                            // skipping this would hand a filtered/no_data
+                            // subscriber full, unfiltered updates on every
+                            // lag event.
+                            let backfill: Vec<Update> =
+                                recent_updates.lock().unwrap().iter().cloned().collect();
+                            for update in backfill {
+                                let Some(update) = filters.apply(&update) else {
+                                    continue;
+                                };
+                                if tx.send(Ok(update)).await.is_err() {
+                                    exit = true;
+                                    break;
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                };
+
+                let Some(update) = filters.apply(&update) else {
+                    continue;
+                };
+
+                if let Err(_err) = tx.send(Ok(update)).await {
                     info!("subscriber stream closed");
                     exit = true;
                 }
@@ -74,3 +176,162 @@ impl Geyser for Service {
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio_stream::StreamExt;
+
+    use super::*;
+    use crate::geyser_proto::AccountWrite;
+
+    fn test_config(broadcast_buffer_size: usize, backfill_on_lag: bool) -> ServiceConfig {
+        ServiceConfig {
+            broadcast_buffer_size,
+            subscriber_buffer_size: 16,
+            lag_buffer_size: 16,
+            backfill_on_lag,
+        }
+    }
+
+    fn account_write(pubkey: u8) -> Update {
+        Update {
+            update_oneof: Some(UpdateOneof::AccountWrite(AccountWrite {
+                pubkey: vec![pubkey],
+                owner: Vec::new(),
+                data: Vec::new(),
+                lamports: 0,
+                rent_epoch: 0,
+                executable: false,
+                tx_signature: None,
+                is_startup: false,
+                slot: 0,
+                write_version: 0,
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_reports_lagged_when_the_broadcast_buffer_overflows() {
+        let service = Service::new(test_config(2, false), Arc::new(AtomicU64::new(0)));
+        let mut stream = Box::pin(
+            service
+                .subscribe(Request::new(SubscribeRequest::default()))
+                .await
+                .unwrap()
+                .into_inner(),
+        );
+
+        // The first item is always the SubscribeResponse handshake.
+        stream.next().await.unwrap().unwrap();
+
+        for pubkey in 0..5u8 {
+            service.sender.send(account_write(pubkey)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let update = stream.next().await.unwrap().unwrap();
+        match update.update_oneof {
+            Some(UpdateOneof::Lagged(Lagged { skipped, .. })) => assert!(skipped > 0),
+            other => panic!("expected Lagged, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribe_backfills_buffered_updates_after_a_lag_when_configured() {
+        let service = Service::new(test_config(2, true), Arc::new(AtomicU64::new(0)));
+        tokio::spawn(Service::run_backfill_recorder(
+            service.sender.clone(),
+            service.recent_updates.clone(),
+            service.lag_buffer_size,
+        ));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        service.sender.send(account_write(100)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let mut stream = Box::pin(
+            service
+                .subscribe(Request::new(SubscribeRequest::default()))
+                .await
+                .unwrap()
+                .into_inner(),
+        );
+        stream.next().await.unwrap().unwrap();
+
+        for pubkey in 0..5u8 {
+            service.sender.send(account_write(pubkey)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let lagged = stream.next().await.unwrap().unwrap();
+        assert!(matches!(lagged.update_oneof, Some(UpdateOneof::Lagged(_))));
+
+        // pubkey 100 was broadcast, and long since evicted from the live
+        // channel (capacity 2) by the time this subscriber lagged -- the
+        // only way to still see it is a replay from `recent_updates`.
+        assert!(collect_account_write_pubkeys(&mut stream, 8)
+            .await
+            .contains(&vec![100]));
+    }
+
+    #[tokio::test]
+    async fn subscribe_does_not_backfill_after_a_lag_when_not_configured() {
+        let service = Service::new(test_config(2, false), Arc::new(AtomicU64::new(0)));
+        tokio::spawn(Service::run_backfill_recorder(
+            service.sender.clone(),
+            service.recent_updates.clone(),
+            service.lag_buffer_size,
+        ));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        service.sender.send(account_write(100)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let mut stream = Box::pin(
+            service
+                .subscribe(Request::new(SubscribeRequest::default()))
+                .await
+                .unwrap()
+                .into_inner(),
+        );
+        stream.next().await.unwrap().unwrap();
+
+        for pubkey in 0..5u8 {
+            service.sender.send(account_write(pubkey)).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let lagged = stream.next().await.unwrap().unwrap();
+        assert!(matches!(lagged.update_oneof, Some(UpdateOneof::Lagged(_))));
+
+        // Without backfill, only what's still in the live channel after the
+        // lag (pubkeys 3 and 4) can follow -- pubkey 100 is long gone from
+        // it and only recoverable via a `recent_updates` replay.
+        assert!(!collect_account_write_pubkeys(&mut stream, 8)
+            .await
+            .contains(&vec![100]));
+    }
+
+    /// Drains up to `max` further items from `stream`, stopping early once no
+    /// item arrives within a short window, and returns the pubkeys of every
+    /// `AccountWrite` seen.
+    async fn collect_account_write_pubkeys(
+        stream: &mut (impl tokio_stream::Stream<Item = Result<Update, Status>> + Unpin),
+        max: usize,
+    ) -> Vec<Vec<u8>> {
+        let mut pubkeys = Vec::new();
+        for _ in 0..max {
+            match tokio::time::timeout(Duration::from_millis(50), stream.next()).await {
+                Ok(Some(Ok(update))) => {
+                    if let Some(UpdateOneof::AccountWrite(write)) = update.update_oneof {
+                        pubkeys.push(write.pubkey);
+                    }
+                }
+                _ => break,
+            }
+        }
+        pubkeys
+    }
+}
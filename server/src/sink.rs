@@ -0,0 +1,224 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use log::*;
+
+use crate::{geyser_proto::AccountWrite, metrics::SinkMetrics};
+
+/// A custom handler for account writes, dispatched to in addition to the
+/// gRPC broadcast -- a postgres writer, a webhook, a derived-metrics
+/// computer, etc.
+#[tonic::async_trait]
+pub trait AccountWriteSink: Send + Sync {
+    async fn process(&self, pubkey: Vec<u8>, account: AccountWrite) -> Result<(), String>;
+}
+
+/// Routes writes for `matched_pubkeys` to `sink`, bounded by
+/// `timeout_interval` so one slow sink can't stall the hot path.
+pub struct AccountWriteRoute {
+    pub matched_pubkeys: Vec<Vec<u8>>,
+    pub sink: Arc<dyn AccountWriteSink>,
+    pub timeout_interval: Duration,
+}
+
+/// Sits between the geyser source and the gRPC broadcast, indexing routes
+/// by matched pubkey so the hot path is a hash lookup. This turns the
+/// connector from a pure pass-through into an extensible processing
+/// pipeline.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<AccountWriteRoute>,
+    by_pubkey: HashMap<Vec<u8>, Vec<usize>>,
+    metrics: Arc<SinkMetrics>,
+}
+
+impl Router {
+    pub fn new(routes: Vec<AccountWriteRoute>) -> Self {
+        let mut by_pubkey: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+        for (index, route) in routes.iter().enumerate() {
+            for pubkey in &route.matched_pubkeys {
+                by_pubkey.entry(pubkey.clone()).or_default().push(index);
+            }
+        }
+        Router {
+            routes,
+            by_pubkey,
+            metrics: Arc::new(SinkMetrics::default()),
+        }
+    }
+
+    /// A handle to this router's dispatch counters, for a caller that wants
+    /// to log or otherwise surface them (see `run_periodic_metrics_log`).
+    pub fn metrics(&self) -> Arc<SinkMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Logs `metrics` on a timer, so a slow or failing sink shows up without
+    /// having to read logs for individual dispatch warnings. Runs until the
+    /// plugin is unloaded and the runtime is dropped.
+    pub async fn run_periodic_metrics_log(metrics: Arc<SinkMetrics>, log_interval: Duration) {
+        loop {
+            tokio::time::sleep(log_interval).await;
+            info!(
+                "sink dispatch: {} successes, {} failures, {} timeouts",
+                metrics.successes(),
+                metrics.failures(),
+                metrics.timeouts()
+            );
+        }
+    }
+
+    /// Fire off every route matching `write.pubkey`. Dispatch is
+    /// fire-and-forget: a slow or failing sink is logged and counted, but
+    /// never blocks the broadcast.
+    pub fn dispatch(&self, write: &AccountWrite) {
+        let route_indices = match self.by_pubkey.get(&write.pubkey) {
+            Some(indices) => indices,
+            None => return,
+        };
+        for &index in route_indices {
+            let route = &self.routes[index];
+            let sink = route.sink.clone();
+            let pubkey = write.pubkey.clone();
+            let account = write.clone();
+            let timeout_interval = route.timeout_interval;
+            let metrics = self.metrics.clone();
+            tokio::spawn(async move {
+                let pubkey_str = || bs58::encode(&pubkey).into_string();
+                match tokio::time::timeout(timeout_interval, sink.process(pubkey.clone(), account))
+                    .await
+                {
+                    Ok(Ok(())) => metrics.record_success(),
+                    Ok(Err(err)) => {
+                        warn!("sink failed for {}: {}", pubkey_str(), err);
+                        metrics.record_failure();
+                    }
+                    Err(_) => {
+                        warn!("sink timed out for {} after {:?}", pubkey_str(), timeout_interval);
+                        metrics.record_timeout();
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn account_write(pubkey: &[u8]) -> AccountWrite {
+        AccountWrite {
+            pubkey: pubkey.to_vec(),
+            owner: Vec::new(),
+            data: Vec::new(),
+            lamports: 0,
+            rent_epoch: 0,
+            executable: false,
+            tx_signature: None,
+            is_startup: false,
+            slot: 0,
+            write_version: 0,
+        }
+    }
+
+    struct RecordingSink {
+        delay: Duration,
+        result: Result<(), String>,
+        calls: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    #[tonic::async_trait]
+    impl AccountWriteSink for RecordingSink {
+        async fn process(&self, pubkey: Vec<u8>, _account: AccountWrite) -> Result<(), String> {
+            tokio::time::sleep(self.delay).await;
+            self.calls.lock().unwrap().push(pubkey);
+            self.result.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn dispatch_ignores_writes_for_unmatched_pubkeys() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::new(RecordingSink {
+            delay: Duration::from_millis(0),
+            result: Ok(()),
+            calls: calls.clone(),
+        });
+        let router = Router::new(vec![AccountWriteRoute {
+            matched_pubkeys: vec![b"tracked".to_vec()],
+            sink,
+            timeout_interval: Duration::from_millis(50),
+        }]);
+
+        router.dispatch(&account_write(b"untracked"));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(calls.lock().unwrap().is_empty());
+        assert_eq!(router.metrics().successes(), 0);
+    }
+
+    #[tokio::test]
+    async fn dispatch_records_success_for_a_fast_matching_sink() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::new(RecordingSink {
+            delay: Duration::from_millis(0),
+            result: Ok(()),
+            calls: calls.clone(),
+        });
+        let router = Router::new(vec![AccountWriteRoute {
+            matched_pubkeys: vec![b"tracked".to_vec()],
+            sink,
+            timeout_interval: Duration::from_millis(50),
+        }]);
+
+        router.dispatch(&account_write(b"tracked"));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(calls.lock().unwrap().as_slice(), &[b"tracked".to_vec()]);
+        assert_eq!(router.metrics().successes(), 1);
+        assert_eq!(router.metrics().failures(), 0);
+        assert_eq!(router.metrics().timeouts(), 0);
+    }
+
+    #[tokio::test]
+    async fn dispatch_records_failure_when_the_sink_errors() {
+        let sink = Arc::new(RecordingSink {
+            delay: Duration::from_millis(0),
+            result: Err("boom".to_string()),
+            calls: Arc::new(Mutex::new(Vec::new())),
+        });
+        let router = Router::new(vec![AccountWriteRoute {
+            matched_pubkeys: vec![b"tracked".to_vec()],
+            sink,
+            timeout_interval: Duration::from_millis(50),
+        }]);
+
+        router.dispatch(&account_write(b"tracked"));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(router.metrics().failures(), 1);
+        assert_eq!(router.metrics().successes(), 0);
+    }
+
+    #[tokio::test]
+    async fn dispatch_records_timeout_for_a_sink_slower_than_timeout_interval() {
+        let sink = Arc::new(RecordingSink {
+            delay: Duration::from_millis(100),
+            result: Ok(()),
+            calls: Arc::new(Mutex::new(Vec::new())),
+        });
+        let router = Router::new(vec![AccountWriteRoute {
+            matched_pubkeys: vec![b"tracked".to_vec()],
+            sink,
+            timeout_interval: Duration::from_millis(10),
+        }]);
+
+        router.dispatch(&account_write(b"tracked"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(router.metrics().timeouts(), 1);
+        assert_eq!(router.metrics().successes(), 0);
+    }
+}